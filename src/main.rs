@@ -1,9 +1,10 @@
 use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::io;
 use std::io::Write;
-use std::process::exit;
 use std::rc::Rc;
-use std::ops::{DerefMut, Deref};
+use std::ops::DerefMut;
 use rand::prelude::ThreadRng;
 use rand::prelude::*;
 
@@ -33,8 +34,12 @@ You:
    Each turn you may move or shoot a crooked arrow.
    Moving:  You can move one room (through one tunnel).
    Arrows:  You have 5 arrows.  You lose when you run out.
-      You can only shoot to nearby rooms.
-      If the arrow hits the wumpus, you win.
+      Crooked arrows can go a maximum of five rooms. You aim by
+      giving the arrow a path, e.g. \"2 5 7\". If a room on your
+      path isn't connected to the arrow's current room, the
+      arrow will instead fly to a random room next to it.
+      If the arrow hits the wumpus, you win. If it hits you,
+      you lose!
 Warnings:
    When you are one room away from a wumpus or hazard, the computer
    says:
@@ -51,9 +56,52 @@ const PITS: usize = 2;
 const ARROWS: usize = 5;
 
 const WAKE_WUMPUS_PROB: f32 = 0.75;
+const MAX_ARROW_PATH: usize = 5;
 
 type RoomNum = usize;
 
+////////
+// IO //
+////////
+
+// Abstracts the game's input/output so the main loop can be driven by
+// something other than a real terminal (tests, scripts, ...).
+trait Io {
+    fn print(&mut self, s: &str);
+    fn read_line(&mut self) -> io::Result<String>;
+
+    fn print_many<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>) {
+        for line in lines {
+            self.print(line);
+        }
+    }
+
+    // Most implementations don't need to pace output; real terminal play does.
+    fn sleep(&mut self, _ms: u64) {}
+}
+
+// The real terminal, wired up to stdin/stdout.
+struct Stdio;
+
+impl Io for Stdio {
+    fn print(&mut self, s: &str) {
+        print!("{}", s);
+        io::stdout().flush().expect("Error flushing");
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stdin closed"));
+        }
+        Ok(input)
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        std::thread::sleep(std::time::Duration::from_millis(ms));
+    }
+}
+
 ////////////
 // PLAYER //
 ////////////
@@ -66,10 +114,11 @@ struct Player {
 
 impl Player {
     fn new(room: RoomNum) -> Self {
-        Player {
-            arrows: ARROWS,
-            room,
-        }
+        Player::with_arrows(room, ARROWS)
+    }
+
+    fn with_arrows(room: RoomNum, arrows: usize) -> Self {
+        Player { room, arrows }
     }
 }
 
@@ -87,7 +136,7 @@ enum Danger {
 #[derive(Default, Debug)]
 struct Room {
     id: RoomNum,
-    neighbours: [Cell<Option<RoomNum>>; ROOM_NEIGHBOURS],
+    neighbours: Vec<Cell<Option<RoomNum>>>,
     dangers: Vec<Danger>,
 }
 
@@ -109,10 +158,78 @@ impl Room {
     }
 }
 
+// Breadth-first reachability check used while generating a maze: true if
+// every room can be reached from room 0 by following tunnels.
+fn all_rooms_reachable(rooms: &[Room]) -> bool {
+    let mut visited: HashSet<RoomNum> = HashSet::new();
+    let mut frontier: VecDeque<RoomNum> = VecDeque::new();
+
+    frontier.push_back(0);
+    visited.insert(0);
+
+    while let Some(room) = frontier.pop_front() {
+        for neighbour in rooms[room].neighbour_ids() {
+            if visited.insert(neighbour) {
+                frontier.push_back(neighbour);
+            }
+        }
+    }
+
+    visited.len() == rooms.len()
+}
+
 //////////
 // MAZE //
 //////////
 
+// The result of flying a crooked arrow along its path.
+#[derive(Debug, PartialEq)]
+enum ArrowOutcome {
+    HitWumpus,
+    HitSelf,
+    Missed,
+}
+
+// A descriptive error from `Maze::parse`: malformed lines, out-of-range
+// neighbours, or a layout that fails the connectivity invariant.
+#[derive(Debug, PartialEq)]
+struct MazeParseError(String);
+
+impl fmt::Display for MazeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for MazeParseError {}
+
+// Parameters for generating a cave: how big it is, how tangled its tunnels
+// are, and how many hazards are hiding in it.
+struct MazeConfig {
+    rooms: usize,
+    neighbours_per_room: usize,
+    bats: usize,
+    pits: usize,
+}
+
+impl MazeConfig {
+    fn new(rooms: usize, neighbours_per_room: usize, bats: usize, pits: usize) -> Self {
+        MazeConfig {
+            rooms,
+            neighbours_per_room,
+            bats,
+            pits,
+        }
+    }
+
+    // The classic "Hunt the Wumpus" numbers: 20 rooms, 3 tunnels each. The
+    // tunnels are wired at random like any other config; the rooms are not
+    // laid out as an actual dodecahedron.
+    fn dodecahedron() -> Self {
+        MazeConfig::new(MAZE_ROOMS, ROOM_NEIGHBOURS, BATS, PITS)
+    }
+}
+
 #[derive(Debug)]
 struct Maze {
     rooms: Vec<Room>,
@@ -120,40 +237,49 @@ struct Maze {
 }
 
 impl Maze {
-    // List of adjacencies used to wire up the dodecahedron.
-    // https://stackoverflow.com/a/44096541/364875
-    const ADJS: [[usize; 3]; 20] = [
-        [1, 4, 7],
-        [0, 2, 9],
-        [1, 3, 11],
-        [2, 4, 13],
-        [0, 3, 5],
-        [4, 6, 14],
-        [5, 7, 16],
-        [0, 6, 8],
-        [7, 9, 17],
-        [1, 8, 10],
-        [9, 11, 18],
-        [2, 10, 12],
-        [11, 13, 19],
-        [3, 12, 14],
-        [5, 13, 15],
-        [14, 16, 19],
-        [6, 15, 17],
-        [8, 16, 18],
-        [10, 17, 19],
-        [12, 15, 18],
-    ];
-
-    // Builds a vector of rooms comprising a dodecahedron.
     fn new(rng: Rc<RefCell<ThreadRng>>) -> Self {
-        let mut rooms: Vec<Room> = (0..MAZE_ROOMS)
+        Maze::from_config(MazeConfig::dodecahedron(), rng)
+    }
+
+    // Builds a cave matching `config`: a random regular connected graph with
+    // `config.neighbours_per_room` tunnels out of every room, then the
+    // wumpus, pits and bats dropped into empty rooms.
+    fn from_config(config: MazeConfig, rng: Rc<RefCell<ThreadRng>>) -> Self {
+        assert!(config.rooms > config.bats + config.pits + 1,
+            "not enough rooms to fit the wumpus and every hazard");
+        assert!((config.rooms * config.neighbours_per_room).is_multiple_of(2),
+            "a regular graph needs rooms * neighbours_per_room to be even");
+
+        let mut rooms: Vec<Room> = (0..config.rooms)
             .map(|idx| Room::new(idx as RoomNum))
             .collect();
 
-        for (i, room) in rooms.iter_mut().enumerate() {
-            for (j, nb) in room.neighbours.iter_mut().enumerate() {
-                nb.set(Some(Maze::ADJS[i][j]));
+        loop {
+            for room in rooms.iter_mut() {
+                room.neighbours.clear();
+            }
+
+            for room in 0..config.rooms {
+                while rooms[room].neighbours.len() < config.neighbours_per_room {
+                    let candidates: Vec<RoomNum> = (0..config.rooms)
+                        .filter(|&other| other != room
+                            && !rooms[room].neighbour_ids().contains(&other)
+                            && rooms[other].neighbours.len() < config.neighbours_per_room)
+                        .collect();
+
+                    let chosen = match candidates.choose(RefCell::borrow_mut(&rng).deref_mut()) {
+                        Some(&other) => other,
+                        None => break,
+                    };
+
+                    rooms[room].neighbours.push(Cell::new(Some(chosen)));
+                    rooms[chosen].neighbours.push(Cell::new(Some(room)));
+                }
+            }
+
+            let fully_wired = rooms.iter().all(|r| r.neighbours.len() == config.neighbours_per_room);
+            if fully_wired && all_rooms_reachable(&rooms) {
+                break;
             }
         }
 
@@ -162,21 +288,139 @@ impl Maze {
             rng,
         };
 
-        // place the wumpus, pits and bats in empty rooms
-        let empty_room = maze.rnd_empty_room();
-        maze.rooms[empty_room].dangers.push(Danger::Wumpus);
+        maze.place_hazards(config.pits, config.bats);
 
-        for _ in 0..PITS {
-            let empty_room = maze.rnd_empty_room();
-            maze.rooms[empty_room].dangers.push(Danger::Pit);
+        maze
+    }
+
+    // Parses a cave layout out of the line-based format written by this
+    // maze's `Display` impl: one line per room, `id:
+    // neighbour,neighbour,neighbour [wumpus|pit|bat]`. Rooms must appear
+    // exactly once each, starting at 0. If no line encodes a wumpus, hazards
+    // are placed at random as usual (`config.rooms`'s worth of bats/pits
+    // isn't known here, so the global defaults are used); a layout that
+    // already names a wumpus is trusted as-is and left untouched.
+    //
+    // This takes `rng` directly instead of being a `std::str::FromStr` impl,
+    // since a freshly-parsed maze still needs a shared rng for gameplay.
+    fn parse(input: &str, rng: Rc<RefCell<ThreadRng>>) -> Result<Self, MazeParseError> {
+        let mut parsed = Vec::new();
+
+        for (line_no, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            parsed.push(Maze::parse_line(line, line_no + 1)?);
         }
 
-        for _ in 0..BATS {
-            let empty_room = maze.rnd_empty_room();
-            maze.rooms[empty_room].dangers.push(Danger::Bat);
+        parsed.sort_by_key(|(id, _, _)| *id);
+
+        let room_count = parsed.len();
+        for (expected_id, (id, _, _)) in parsed.iter().enumerate() {
+            if *id != expected_id {
+                return Err(MazeParseError(format!(
+                    "expected room {} but found room {} (every room from 0 must appear exactly once)",
+                    expected_id, id)));
+            }
         }
 
-        maze
+        let mut rooms = Vec::with_capacity(room_count);
+        for (id, neighbours, dangers) in parsed {
+            for &neighbour in &neighbours {
+                if neighbour >= room_count {
+                    return Err(MazeParseError(format!(
+                        "room {} references out-of-range neighbour {}", id, neighbour)));
+                }
+            }
+
+            rooms.push(Room {
+                id,
+                neighbours: neighbours.into_iter().map(|n| Cell::new(Some(n))).collect(),
+                dangers,
+            });
+        }
+
+        for room in &rooms {
+            for neighbour in room.neighbour_ids() {
+                if !rooms[neighbour].neighbour_ids().contains(&room.id) {
+                    return Err(MazeParseError(format!(
+                        "room {} lists room {} as a neighbour, but room {} doesn't list room {} back (tunnels must run both ways)",
+                        room.id, neighbour, neighbour, room.id)));
+                }
+            }
+        }
+
+        if !all_rooms_reachable(&rooms) {
+            return Err(MazeParseError("cave layout isn't fully connected".to_string()));
+        }
+
+        let mut maze = Maze { rooms, rng };
+
+        if !maze.rooms.iter().any(|r| r.dangers.contains(&Danger::Wumpus)) {
+            let empty_rooms = maze.rooms.iter().filter(|r| r.dangers.is_empty()).count();
+            let hazards = 1 + PITS + BATS;
+
+            // Leave one empty room over for the player to start in.
+            if empty_rooms <= hazards {
+                return Err(MazeParseError(format!(
+                    "cave has {} empty room(s), but placing the wumpus, {} pit(s) and {} bat(s) needs at least {} (with one room left over for the player)",
+                    empty_rooms, PITS, BATS, hazards + 1)));
+            }
+
+            maze.place_hazards(PITS, BATS);
+        }
+
+        Ok(maze)
+    }
+
+    // Parses one `id: neighbour,neighbour,... [wumpus|pit|bat]` line.
+    fn parse_line(line: &str, line_no: usize) -> Result<(RoomNum, Vec<RoomNum>, Vec<Danger>), MazeParseError> {
+        let (id_part, rest) = line.split_once(':')
+            .ok_or_else(|| MazeParseError(format!("line {}: expected \"id: neighbours\"", line_no)))?;
+
+        let id: RoomNum = id_part.trim().parse()
+            .map_err(|_| MazeParseError(format!("line {}: invalid room id \"{}\"", line_no, id_part.trim())))?;
+
+        let mut fields = rest.trim().splitn(2, ' ');
+        let neighbours_field = fields.next().unwrap_or("").trim();
+        let danger_field = fields.next().map(str::trim).unwrap_or("");
+
+        let neighbours = if neighbours_field.is_empty() {
+            Vec::new()
+        } else {
+            neighbours_field.split(',')
+                .map(|n| n.trim().parse::<RoomNum>()
+                    .map_err(|_| MazeParseError(format!("line {}: invalid neighbour \"{}\"", line_no, n.trim()))))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let dangers = match danger_field {
+            "" => Vec::new(),
+            "wumpus" => vec![Danger::Wumpus],
+            "pit" => vec![Danger::Pit],
+            "bat" => vec![Danger::Bat],
+            other => return Err(MazeParseError(format!("line {}: unknown hazard \"{}\"", line_no, other))),
+        };
+
+        Ok((id, neighbours, dangers))
+    }
+
+    // Drops the wumpus, then `pits` pits and `bats` bats, into empty rooms.
+    fn place_hazards(&mut self, pits: usize, bats: usize) {
+        let empty_room = self.rnd_empty_room();
+        self.rooms[empty_room].dangers.push(Danger::Wumpus);
+
+        for _ in 0..pits {
+            let empty_room = self.rnd_empty_room();
+            self.rooms[empty_room].dangers.push(Danger::Pit);
+        }
+
+        for _ in 0..bats {
+            let empty_room = self.rnd_empty_room();
+            self.rooms[empty_room].dangers.push(Danger::Bat);
+        }
     }
 
     fn rnd_empty_room(&mut self) -> RoomNum {
@@ -208,6 +452,16 @@ impl Maze {
         Some(**empty_neighbour)
     }
 
+    // Picks any neighbour of `room` at random, danger or not. Used for the
+    // crooked arrow's trajectory, which doesn't care what's waiting for it.
+    fn rnd_neighbour(&mut self, room: RoomNum) -> RoomNum {
+        let neighbour_ids = self.rooms[room].neighbour_ids();
+
+        *neighbour_ids
+            .choose(RefCell::borrow_mut(&self.rng).deref_mut())
+            .unwrap()
+    }
+
     fn describe_room(&self, room: RoomNum) -> String {
         let mut description = format!("You are in room #{}", room);
 
@@ -250,35 +504,193 @@ impl Maze {
 
         Err(())
     }
+
+    // Flies a crooked arrow leg by leg along `path`, starting from the
+    // shooter's room. Each leg goes to the requested room if it's adjacent
+    // to the arrow's current position, otherwise the arrow goes astray into
+    // a random adjacent room instead, exactly like the original "Hunt the
+    // Wumpus" crooked arrow.
+    fn fire_arrow(&mut self, path: Vec<RoomNum>, shooter_room: RoomNum) -> ArrowOutcome {
+        let mut arrow_room = shooter_room;
+
+        for requested_room in path {
+            arrow_room = if self.rooms[arrow_room].neighbour_ids().contains(&requested_room) {
+                requested_room
+            } else {
+                self.rnd_neighbour(arrow_room)
+            };
+
+            if arrow_room == self.wumpus_room() {
+                return ArrowOutcome::HitWumpus;
+            }
+            if arrow_room == shooter_room {
+                return ArrowOutcome::HitSelf;
+            }
+        }
+
+        ArrowOutcome::Missed
+    }
+
+    fn wumpus_room(&self) -> RoomNum {
+        self.rooms.iter()
+            .find(|r| r.dangers.contains(&Danger::Wumpus))
+            .unwrap()
+            .id
+    }
+
+    // Breadth-first search over the neighbour adjacency, returning the
+    // shortest room-to-room path from `from` to `to` (inclusive of both ends).
+    fn shortest_path(&self, from: RoomNum, to: RoomNum) -> Option<Vec<RoomNum>> {
+        let mut frontier: VecDeque<RoomNum> = VecDeque::new();
+        let mut came_from: HashMap<RoomNum, RoomNum> = HashMap::new();
+        let mut visited: HashSet<RoomNum> = HashSet::new();
+
+        frontier.push_back(from);
+        visited.insert(from);
+
+        while let Some(room) = frontier.pop_front() {
+            if room == to {
+                let mut path = vec![room];
+                while let Some(&prev) = came_from.get(path.last().unwrap()) {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for neighbour in self.rooms[room].neighbour_ids() {
+                if visited.insert(neighbour) {
+                    came_from.insert(neighbour, room);
+                    frontier.push_back(neighbour);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn distance_to_wumpus(&self, from: RoomNum) -> usize {
+        self.shortest_path(from, self.wumpus_room())
+            .map(|path| path.len() - 1)
+            .unwrap_or(0)
+    }
+}
+
+// Serializes this cave into the format `Maze::parse` reads back: one line
+// per room, `id: neighbour,neighbour,neighbour [wumpus|pit|bat]`.
+impl fmt::Display for Maze {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.rooms.iter()
+            .map(|room| {
+                let neighbours = room.neighbour_ids().iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+
+                let danger = match room.dangers.first() {
+                    Some(Danger::Wumpus) => " wumpus",
+                    Some(Danger::Pit) => " pit",
+                    Some(Danger::Bat) => " bat",
+                    None => "",
+                };
+
+                format!("{}: {}{}", room.id, neighbours, danger)
+            })
+            .collect();
+
+        write!(f, "{}", lines.join("\n"))
+    }
 }
 
 #[test]
 fn test_maze_connected() {
-    use std::collections::HashSet;
     let rng = Rc::new(RefCell::new(rand::thread_rng()));
     let maze = Maze::new(rng.clone());
     let n = maze.rooms.len();
 
-    fn exists_path(
-        i: RoomNum,
-        j: RoomNum,
-        vis: &mut HashSet<RoomNum>,
-        maze: &Maze)
-        -> bool
-    {
-        if i == j {
-            return true;
-        }
-        vis.insert(i);
-        maze.rooms[i].neighbours.iter().any(|neighbour| {
-            // Check that all rooms have three neighbors.
-            let k = neighbour.get().unwrap();
-            !vis.contains(&k) && exists_path(k, j, vis, maze)
-        })
-    }
     for i in 0..n {
         for j in 0..n {
-            assert!(exists_path(i, j, &mut HashSet::new(), &maze));
+            assert!(maze.shortest_path(i, j).is_some());
+        }
+    }
+}
+
+#[test]
+fn test_shortest_path_to_wumpus() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let maze = Maze::new(rng.clone());
+    let wumpus_room = maze.wumpus_room();
+
+    let path = maze.shortest_path(wumpus_room, wumpus_room).unwrap();
+    assert_eq!(path, vec![wumpus_room]);
+    assert_eq!(maze.distance_to_wumpus(wumpus_room), 0);
+
+    for neighbour in maze.rooms[wumpus_room].neighbour_ids() {
+        assert_eq!(maze.distance_to_wumpus(neighbour), 1);
+    }
+}
+
+//////////////
+// COMMANDS //
+//////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Move,
+    Shoot,
+    Help,
+    Quit,
+    Hint,
+}
+
+impl Command {
+    // Matches the word a player types to register a new alias, e.g.
+    // "alias go move" parses "move" into `Command::Move`.
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "move" => Some(Command::Move),
+            "shoot" => Some(Command::Shoot),
+            "help" => Some(Command::Help),
+            "quit" => Some(Command::Quit),
+            "hint" => Some(Command::Hint),
+            _ => None,
+        }
+    }
+}
+
+// Maps many input words to one `Command`, so players can rebind or
+// abbreviate their controls instead of being stuck with a single letter.
+struct CommandAliases {
+    aliases: Vec<(HashSet<String>, Command)>,
+}
+
+impl CommandAliases {
+    fn with_defaults() -> Self {
+        let words = |words: &[&str]| words.iter().map(|w| w.to_string()).collect();
+
+        CommandAliases {
+            aliases: vec![
+                (words(&["m", "move", "go"]), Command::Move),
+                (words(&["s", "shoot"]), Command::Shoot),
+                (words(&["h", "help"]), Command::Help),
+                (words(&["q", "quit"]), Command::Quit),
+                (words(&["hint"]), Command::Hint),
+            ],
+        }
+    }
+
+    fn lookup(&self, input: &str) -> Option<Command> {
+        self.aliases.iter()
+            .find(|(words, _)| words.contains(input))
+            .map(|(_, command)| *command)
+    }
+
+    fn register(&mut self, word: &str, command: Command) {
+        for (words, _) in self.aliases.iter_mut() {
+            words.remove(word);
+        }
+        if let Some((words, _)) = self.aliases.iter_mut().find(|(_, c)| *c == command) {
+            words.insert(word.to_string());
         }
     }
 }
@@ -294,123 +706,403 @@ enum Status {
     Shooting,
 }
 
-fn main() {
-    let rng = Rc::new(RefCell::new(rand::thread_rng()));
-    let mut maze = Maze::new(rng.clone());
-    let mut player = Player::new(maze.rnd_empty_room());
-    let mut status = Status::Normal;
+fn describe(io: &mut impl Io, maze: &Maze, player: &Player) {
+    let room_description = format!("{}\n", maze.describe_room(player.room));
+    io.print_many([room_description.as_str(), "What do you want to do? (m)ove or (s)hoot?\n"]);
+}
 
-    let describe = |maze: &Maze, player: &Player| {
-        println!("{}", maze.describe_room(player.room));
-        println!("What do you want to do? (m)ove or (s)hoot?");
-    };
+fn prompt(io: &mut impl Io) {
+    // A short beat before the prompt reappears, so a real terminal doesn't
+    // dump the next turn on top of what the player just read.
+    io.sleep(300);
+    io.print("> ");
+}
 
-    let prompt = || {
-        print!("> ");
-        io::stdout().flush().expect("Error flushing");
-    };
+// Drives a full game from the given starting state until the player wins,
+// loses, quits, or the input stream runs dry. Taking an `impl Io` instead of
+// touching stdin/stdout directly is what lets this be exercised by tests.
+fn run_game(io: &mut impl Io, mut maze: Maze, mut player: Player) {
+    let mut status = Status::Normal;
+    let mut aliases = CommandAliases::with_defaults();
 
-    describe(&maze, &player);
-    prompt();
+    describe(io, &maze, &player);
+    prompt(io);
 
     // main loop
     loop {
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Cannot read from stdin");
+        let input = match io.read_line() {
+            Ok(input) => input,
+            Err(_) => return,
+        };
         let input: &str = &input.trim().to_lowercase();
 
         match status {
             Status::Quitting => {
                 match input {
                     "y" => {
-                        println!("Goodbye, braveheart!");
-                        exit(0);
+                        io.print("Goodbye, braveheart!\n");
+                        return;
                     }
                     "n" => {
-                        println!("Good. the Wumpus is looking for you!");
+                        io.print("Good. the Wumpus is looking for you!\n");
                         status = Status::Normal;
                     }
-                    _ => println!("That doesn't make any sense")
+                    _ => io.print("That doesn't make any sense\n")
                 }
             }
             Status::Moving => {
                 if let Ok(room) = maze.parse_room(input, player.room) {
                     if maze.rooms[room].dangers.contains(&Danger::Wumpus) {
-                        println!("The wumpus ate you up!\nGAME OVER");
-                        exit(0);
+                        io.print("The wumpus ate you up!\nGAME OVER\n");
+                        return;
                     } else if maze.rooms[room].dangers.contains(&Danger::Pit) {
-                        println!("You fall into a bottomless pit!\nGAME OVER");
-                        exit(0);
+                        io.print("You fall into a bottomless pit!\nGAME OVER\n");
+                        return;
                     } else if maze.rooms[room].dangers.contains(&Danger::Bat) {
-                        println!("The bats whisk you away!");
+                        io.print("The bats whisk you away!\n");
                         player.room = maze.rnd_empty_room();
                     } else {
                         player.room = room;
                     }
 
                     status = Status::Normal;
-                    describe(&maze, &player);
+                    describe(io, &maze, &player);
                 } else {
-                    println!("There are no tunnels from here to that room. Where do you wanto do go?");
+                    io.print("There are no tunnels from here to that room. Where do you wanto do go?\n");
                 }
             }
             Status::Shooting => {
-                if let Ok(room) = maze.parse_room(input, player.room) {
-                    if maze.rooms[room].dangers.contains(&Danger::Wumpus) {
-                        println!("YOU KILLED THE WUMPUS! GOOD JOB, BUDDY!!!");
-                        exit(0);
-                    } else {
-                        // 75% chances of waking up the wumpus that would go into another room
-                        if RefCell::borrow_mut(rng.deref()).gen::<f32>() < WAKE_WUMPUS_PROB {
-                            let wumpus_room = maze.rooms.iter()
-                                .find(|r| r.dangers.contains(&Danger::Wumpus))
-                                .unwrap()
-                                .id;
-
-                            if let Some(new_wumpus_room) = maze.rnd_empty_neighbour(wumpus_room) {
-                                if new_wumpus_room == player.room {
-                                    println!("You woke up the wumpus and he ate you!\nGAME OVER");
-                                    exit(1);
+                let path: Option<Vec<RoomNum>> = input.split_whitespace()
+                    .take(MAX_ARROW_PATH)
+                    .map(|room| room.parse().ok())
+                    .collect();
+
+                match path.filter(|path| !path.is_empty()) {
+                    Some(path) => match maze.fire_arrow(path, player.room) {
+                        ArrowOutcome::HitWumpus => {
+                            io.print("YOU KILLED THE WUMPUS! GOOD JOB, BUDDY!!!\n");
+                            return;
+                        }
+                        ArrowOutcome::HitSelf => {
+                            io.print("The crooked arrow flew wild and hit you!\nGAME OVER\n");
+                            return;
+                        }
+                        ArrowOutcome::Missed => {
+                            // 75% chances of waking up the wumpus that would go into another room
+                            if RefCell::borrow_mut(&maze.rng).gen::<f32>() < WAKE_WUMPUS_PROB {
+                                let wumpus_room = maze.wumpus_room();
+
+                                if let Some(new_wumpus_room) = maze.rnd_empty_neighbour(wumpus_room) {
+                                    if new_wumpus_room == player.room {
+                                        io.print("You woke up the wumpus and he ate you!\nGAME OVER\n");
+                                        return;
+                                    }
+
+                                    maze.rooms[wumpus_room].dangers.retain(|d| d != &Danger::Wumpus);
+                                    maze.rooms[new_wumpus_room].dangers.push(Danger::Wumpus);
+                                    io.print("You heard a rumbling in a nearby cavern.\n");
                                 }
+                            }
 
-                                maze.rooms[wumpus_room].dangers.retain(|d| d != &Danger::Wumpus);
-                                maze.rooms[new_wumpus_room].dangers.push(Danger::Wumpus);
-                                println!("You heard a rumbling in a nearby cavern.");
+                            player.arrows -= 1;
+                            if player.arrows == 0 {
+                                io.print("You ran out of arrows.\nGAME OVER\n");
+                                return;
                             }
-                        }
 
-                        player.arrows -= 1;
-                        if  player.arrows == 0 {
-                            println!("You ran out of arrows.\nGAME OVER");
-                            exit(1);
+                            status = Status::Normal;
                         }
-
-                        status = Status::Normal;
                     }
-                } else {
-                    println!("There are no tunnels from here to that room. Where do you wanto do shoot?");
+                    None => {
+                        io.print("Give the arrow's path as room numbers, e.g. \"2 5 7\". Where do you want to shoot?\n");
+                    }
                 }
             }
             _ => {
-                match input {
-                    "h" => println!("{}", HELP),
-                    "q" => {
-                        println!("Are you so easily scared? [y/n]");
-                        status = Status::Quitting;
-                    }
-                    "m" => {
-                        println!("Where?");
-                        status = Status::Moving;
+                let mut words = input.splitn(3, ' ');
+                match (words.next(), words.next(), words.next()) {
+                    (Some("alias"), Some(word), Some(command_name)) => {
+                        match Command::from_name(command_name) {
+                            Some(command) => {
+                                aliases.register(word, command);
+                                io.print(&format!("Got it. \"{}\" now means {:?}.\n", word, command));
+                            }
+                            None => io.print("I don't know that command.\n"),
+                        }
                     }
-                    "s" => {
-                        println!("Where?");
-                        status = Status::Shooting;
+                    _ => {
+                        match aliases.lookup(input) {
+                            Some(Command::Help) => io.print(&format!("{}\n", HELP)),
+                            Some(Command::Quit) => {
+                                io.print("Are you so easily scared? [y/n]\n");
+                                status = Status::Quitting;
+                            }
+                            Some(Command::Move) => {
+                                io.print("Where?\n");
+                                status = Status::Moving;
+                            }
+                            Some(Command::Shoot) => {
+                                io.print("Where?\n");
+                                status = Status::Shooting;
+                            }
+                            Some(Command::Hint) => {
+                                let wumpus_room = maze.wumpus_room();
+                                match maze.shortest_path(player.room, wumpus_room) {
+                                    Some(path) if path.len() > 1 => {
+                                        io.print(&format!(
+                                            "Your instincts point you towards room #{}. ({} rooms from the wumpus.)\n",
+                                            path[1], maze.distance_to_wumpus(player.room)));
+                                    }
+                                    _ => io.print("You are in the wumpus's room!\n"),
+                                }
+                            }
+                            None => io.print("That doesn't make any sense\n")
+                        }
                     }
-                    _ => println!("That doesn't make any sense")
                 }
             }
         }
 
-        prompt();
+        prompt(io);
+    }
+}
+
+// Looks for `--load <file>` among the command-line arguments and returns
+// the file path, if any.
+fn load_flag(args: &[String]) -> Option<&str> {
+    args.iter()
+        .position(|arg| arg == "--load")
+        .and_then(|idx| args.get(idx + 1))
+        .map(String::as_str)
+}
+
+fn main() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let args: Vec<String> = std::env::args().collect();
+
+    let mut maze = match load_flag(&args) {
+        Some(path) => {
+            let cave = std::fs::read_to_string(path)
+                .unwrap_or_else(|err| panic!("Could not read cave file \"{}\": {}", path, err));
+
+            Maze::parse(&cave, rng)
+                .unwrap_or_else(|err| panic!("Could not load cave file \"{}\": {}", path, err))
+        }
+        None => Maze::new(rng),
+    };
+
+    let player = Player::new(maze.rnd_empty_room());
+
+    run_game(&mut Stdio, maze, player);
+}
+
+// Feeds a scripted sequence of inputs into `run_game` and captures everything
+// it prints, so a full playthrough can be asserted on without touching real
+// stdin/stdout.
+#[cfg(test)]
+struct ScriptedIo {
+    inputs: VecDeque<String>,
+    output: String,
+}
+
+#[cfg(test)]
+impl ScriptedIo {
+    fn new(inputs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        ScriptedIo {
+            inputs: inputs.into_iter().map(Into::into).collect(),
+            output: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Io for ScriptedIo {
+    fn print(&mut self, s: &str) {
+        self.output.push_str(s);
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        self.inputs.pop_front()
+            .map(|line| line + "\n")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "no more scripted input"))
+    }
+}
+
+#[test]
+fn test_falling_into_pit_ends_the_game() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let maze = Maze::new(rng.clone());
+    let pit_room = maze.rooms.iter()
+        .find(|r| r.dangers.contains(&Danger::Pit))
+        .unwrap()
+        .id;
+    let start_room = maze.rooms.iter()
+        .find(|r| r.neighbour_ids().contains(&pit_room))
+        .unwrap()
+        .id;
+    let player = Player::new(start_room);
+
+    let mut io = ScriptedIo::new(vec!["m".to_string(), pit_room.to_string()]);
+    run_game(&mut io, maze, player);
+
+    assert!(io.output.contains("bottomless pit"));
+    assert!(io.output.contains("GAME OVER"));
+}
+
+#[test]
+fn test_custom_alias_triggers_movement() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let maze = Maze::new(rng.clone());
+    let pit_room = maze.rooms.iter()
+        .find(|r| r.dangers.contains(&Danger::Pit))
+        .unwrap()
+        .id;
+    let start_room = maze.rooms.iter()
+        .find(|r| r.neighbour_ids().contains(&pit_room))
+        .unwrap()
+        .id;
+    let player = Player::new(start_room);
+
+    let mut io = ScriptedIo::new(vec![
+        "alias g move".to_string(),
+        "g".to_string(),
+        pit_room.to_string(),
+    ]);
+    run_game(&mut io, maze, player);
+
+    assert!(io.output.contains("now means Move"));
+    assert!(io.output.contains("bottomless pit"));
+}
+
+#[test]
+fn test_hint_points_towards_the_wumpus() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let maze = Maze::new(rng.clone());
+    let wumpus_room = maze.wumpus_room();
+    let start_room = if wumpus_room == 0 { 1 } else { 0 };
+    let expected_next_room = maze.shortest_path(start_room, wumpus_room).unwrap()[1];
+    let player = Player::new(start_room);
+
+    let mut io = ScriptedIo::new(vec!["hint".to_string(), "q".to_string(), "y".to_string()]);
+    run_game(&mut io, maze, player);
+
+    assert!(io.output.contains(&format!("room #{}", expected_next_room)));
+}
+
+#[test]
+fn test_crooked_arrow_hits_wumpus_along_the_path() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let mut maze = Maze::new(rng.clone());
+    let wumpus_room = maze.wumpus_room();
+    let start_room = if wumpus_room == 0 { 1 } else { 0 };
+    let legs = maze.shortest_path(start_room, wumpus_room).unwrap()[1..].to_vec();
+
+    // Fire along the exact shortest path to the wumpus: every leg is a
+    // real tunnel, so the crooked-arrow logic never has to improvise.
+    let outcome = maze.fire_arrow(legs, start_room);
+
+    assert_eq!(outcome, ArrowOutcome::HitWumpus);
+}
+
+#[test]
+fn test_crooked_arrow_can_fly_back_and_hit_the_shooter() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let mut maze = Maze::new(rng.clone());
+    let wumpus_room = maze.wumpus_room();
+
+    // Pick a shooter room that isn't the wumpus's room and isn't next to
+    // it, so the first leg below can't accidentally hit the wumpus.
+    let shooter_room = (0..MAZE_ROOMS)
+        .find(|&room| room != wumpus_room && !maze.rooms[room].neighbour_ids().contains(&wumpus_room))
+        .unwrap();
+    let neighbour_room = maze.rooms[shooter_room].neighbour_ids()[0];
+
+    let outcome = maze.fire_arrow(vec![neighbour_room, shooter_room], shooter_room);
+
+    assert_eq!(outcome, ArrowOutcome::HitSelf);
+}
+
+#[test]
+fn test_maze_round_trips_through_display_and_parse() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let config = MazeConfig::new(8, 3, 1, 1);
+    let original = Maze::from_config(config, rng.clone());
+    let text = original.to_string();
+
+    let loaded = Maze::parse(&text, rng).unwrap();
+
+    assert_eq!(loaded.rooms.len(), original.rooms.len());
+    for (original_room, loaded_room) in original.rooms.iter().zip(loaded.rooms.iter()) {
+        let mut original_neighbours = original_room.neighbour_ids();
+        let mut loaded_neighbours = loaded_room.neighbour_ids();
+        original_neighbours.sort();
+        loaded_neighbours.sort();
+
+        assert_eq!(original_neighbours, loaded_neighbours);
+        assert_eq!(original_room.dangers, loaded_room.dangers);
+    }
+}
+
+#[test]
+fn test_parse_rejects_out_of_range_neighbour() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let cave = "0: 1,2,5\n1: 0\n2: 0";
+
+    let err = Maze::parse(cave, rng).unwrap_err();
+    assert!(err.0.contains("out-of-range"));
+}
+
+#[test]
+fn test_parse_rejects_asymmetric_adjacency() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    // room 0 points at room 1, but room 1 doesn't point back.
+    let cave = "0: 1,2\n1: 2\n2: 0,1";
+
+    let err = Maze::parse(cave, rng).unwrap_err();
+    assert!(err.0.contains("doesn't list room"));
+}
+
+#[test]
+fn test_parse_rejects_disconnected_layout() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let cave = "0: 1\n1: 0\n2: 3\n3: 2";
+
+    let err = Maze::parse(cave, rng).unwrap_err();
+    assert!(err.0.contains("connected"));
+}
+
+#[test]
+fn test_parse_rejects_cave_too_small_for_default_hazards() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    // A valid, connected, hazard-free triangle, but far too small to fit a
+    // randomly-placed wumpus, the default pits and bats, and the player.
+    let cave = "0: 1,2\n1: 0,2\n2: 0,1";
+
+    let err = Maze::parse(cave, rng).unwrap_err();
+    assert!(err.0.contains("empty room"));
+}
+
+#[test]
+fn test_parse_honours_explicit_hazard_placement() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let cave = "0: 1,2 pit\n1: 0,2 wumpus\n2: 0,1 bat";
+
+    let maze = Maze::parse(cave, rng).unwrap();
+
+    assert_eq!(maze.wumpus_room(), 1);
+    assert!(maze.rooms[0].dangers.contains(&Danger::Pit));
+    assert!(maze.rooms[2].dangers.contains(&Danger::Bat));
+}
+
+#[test]
+fn test_maze_from_custom_config_honours_room_and_degree_counts() {
+    let rng = Rc::new(RefCell::new(rand::thread_rng()));
+    let config = MazeConfig::new(12, 4, 1, 1);
+    let maze = Maze::from_config(config, rng);
+
+    assert_eq!(maze.rooms.len(), 12);
+    for room in &maze.rooms {
+        assert_eq!(room.neighbours.len(), 4);
     }
+    assert!(all_rooms_reachable(&maze.rooms));
 }